@@ -4,13 +4,25 @@ use core::{
     pin::Pin,
     task::{Context, Poll, Waker},
 };
+use futures_core::Stream;
 use std::sync::{Arc, Mutex};
+use thiserror::Error;
 
 #[derive(Debug)]
 enum Token<T> {
     New,
-    Pending(Waker),
+    Pending(Vec<Waker>),
     Complete(T),
+    Disconnected,
+}
+
+/// The error returned when awaiting a [`CompletionToken`] after every other
+/// clone has been dropped while no [`set`](CompletionToken::set) value was
+/// waiting to be taken.
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+pub enum RecvError {
+    #[error("the CompletionToken was dropped before it was completed")]
+    Disconnected,
 }
 
 #[derive(Debug, Clone)]
@@ -42,14 +54,16 @@ impl<T> CompletionToken<T> {
 
         let mut token = Token::Complete(value);
         match inner {
-            Token::New => {
+            Token::New | Token::Disconnected => {
                 mem::swap(inner, &mut token);
             }
-            Token::Pending(_waker) => {
+            Token::Pending(_wakers) => {
                 mem::swap(inner, &mut token);
 
-                if let Token::Pending(waker) = token {
-                    waker.wake();
+                if let Token::Pending(wakers) = token {
+                    for waker in wakers {
+                        waker.wake();
+                    }
                 }
             }
             Token::Complete(_old_value) => {
@@ -65,25 +79,41 @@ impl<T> Default for CompletionToken<T> {
     }
 }
 
-impl<T> Future for CompletionToken<T> {
-    type Output = T;
-
-    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+impl<T> CompletionToken<T> {
+    /// Shared state machine driving both [`Future::poll`] and
+    /// [`Stream::poll_next`]: register a waker while incomplete, take the
+    /// value once `Complete`, or report disconnection if nothing else is
+    /// left to call `set`.
+    ///
+    /// Disconnection itself is recorded by [`Drop`], which transitions the
+    /// state under this same lock, not by snapshotting `Arc::strong_count` here: reading the count
+    /// before acquiring the lock would let a concurrent `drop` finish its
+    /// own check-then-lock in between, so a disconnect could be missed and
+    /// a waker registered that nothing will ever wake again.
+    fn poll_token(&self, cx: &mut Context) -> Poll<Result<T, RecvError>> {
         let inner = &mut *self.inner.lock().expect("poll inner");
 
         match inner {
             // Future is incomplete, so register a waker
             Token::New => {
-                let mut token = Token::Pending(cx.waker().clone());
+                let mut token = Token::Pending(vec![cx.waker().clone()]);
                 mem::swap(inner, &mut token);
 
-                // Another task will need to call CompletionToken::wake()
+                // Another task will need to call CompletionToken::set()
                 // to trigger another poll() from the executor
                 Poll::Pending
             }
 
-            // Future is already being polled
-            Token::Pending(_waker) => Poll::Pending,
+            // Future is already being polled by at least one other waiter.
+            // Register our own waker too, unless an equivalent one is
+            // already stored, so every concurrent waiter gets woken.
+            Token::Pending(wakers) => {
+                if !wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+                    wakers.push(cx.waker().clone());
+                }
+
+                Poll::Pending
+            }
 
             // The future has completed, take the value
             Token::Complete(_value) => {
@@ -94,14 +124,73 @@ impl<T> Future for CompletionToken<T> {
                 // We hold the lock on inner,
                 // and have already matched it as Complete.
                 if let Token::Complete(value) = token {
-                    Poll::Ready(value)
+                    Poll::Ready(Ok(value))
                 } else {
                     // Rare if this occurs, possible race?
-                    let mut token = Token::Pending(cx.waker().clone());
+                    let mut token = Token::Pending(vec![cx.waker().clone()]);
                     mem::swap(inner, &mut token);
                     Poll::Pending
                 }
             },
+
+            Token::Disconnected => Poll::Ready(Err(RecvError::Disconnected)),
+        }
+    }
+}
+
+impl<T> Future for CompletionToken<T> {
+    type Output = Result<T, RecvError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        self.poll_token(cx)
+    }
+}
+
+/// Repeated completions can be drained through the `Stream` adapter rather
+/// than re-awaiting the bare future after every `set`:
+///
+/// ```ignore
+/// while let Some(value) = token.next().await {
+///     // handle `value`
+/// }
+/// ```
+///
+/// The stream ends once every other handle able to call `set` has been
+/// dropped.
+impl<T> Stream for CompletionToken<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        self.poll_token(cx).map(Result::ok)
+    }
+}
+
+impl<T> Drop for CompletionToken<T> {
+    fn drop(&mut self) {
+        // If this drop is about to leave exactly one handle behind, that
+        // handle can no longer rely on another clone to call `set`.
+        // The handle count is read before locking, but transitioning the
+        // state happens under the same lock `poll_token` uses, so a
+        // concurrent poll can't register a waker that this drop has
+        // already passed by.
+        if Arc::strong_count(&self.inner) == 2 {
+            if let Ok(mut inner) = self.inner.lock() {
+                match &mut *inner {
+                    Token::New => {
+                        *inner = Token::Disconnected;
+                    }
+                    Token::Pending(wakers) => {
+                        for waker in wakers.drain(..) {
+                            waker.wake();
+                        }
+                        *inner = Token::Disconnected;
+                    }
+                    // Leave an unconsumed value for the remaining handle to
+                    // take; disconnection only matters once there is
+                    // nothing left to poll for.
+                    Token::Complete(_) | Token::Disconnected => {}
+                }
+            }
         }
     }
 }
@@ -113,6 +202,7 @@ impl<T: PartialEq> PartialEq for CompletionToken<T> {
 
         match (this, that) {
             (Token::New, Token::New) => true,
+            (Token::Disconnected, Token::Disconnected) => true,
 
             // Compare pointers
             (Token::Pending(_), Token::Pending(_)) => {
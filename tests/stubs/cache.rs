@@ -0,0 +1,154 @@
+use crate::Response;
+use completion_token::CompletionToken;
+use linked_hash_map::LinkedHashMap;
+
+/// Anything stored in the response cache must be able to report how much
+/// space it occupies, so the cache can be bounded by more than just entry
+/// count.
+pub trait Weight {
+    fn weight(&self) -> usize;
+}
+
+impl Weight for Response {
+    fn weight(&self) -> usize {
+        self.value.len()
+    }
+}
+
+/// A single slot in the cache: either a request that is still in flight, or
+/// the response it eventually resolved to.
+///
+/// Every concurrent caller asking for the same key gets its own
+/// `CompletionToken` to await, but only the first ("leading") caller's
+/// token is ever sent to the worker. Everyone else's token is parked in
+/// `followers` and completed by the leader once the real response lands,
+/// so identical requests coalesce onto a single trip to the worker without
+/// racing each other over who gets to consume it.
+#[derive(Debug)]
+enum CacheEntry {
+    Pending { followers: Vec<CompletionToken<Response>> },
+    Ready(Response),
+}
+
+/// The result of [`Cache::get_or_subscribe`]: either a cached response, a
+/// `CompletionToken` to await (along with whether the caller is the leader
+/// responsible for actually dispatching the request), or a rejection
+/// because too many distinct requests are already in flight.
+#[derive(Debug)]
+pub enum Lookup {
+    Ready(Response),
+    Pending(CompletionToken<Response>, bool),
+    Rejected,
+}
+
+/// A bounded, weight-aware LRU cache of [`Response`]s, keyed by request
+/// value. In-flight entries count as zero weight and are never evicted
+/// (evicting one would strand its followers without freeing anything), so
+/// `entry_limit` alone can't bound them the way it bounds resolved entries.
+/// Instead `get_or_subscribe` reuses `entry_limit` as a cap on the number of
+/// distinct in-flight requests too: once that many are pending, new leaders
+/// are rejected rather than admitted without bound. Followers joining an
+/// already-pending request don't count against the cap, since they don't
+/// add a new entry.
+#[derive(Debug)]
+pub struct Cache {
+    entries: LinkedHashMap<String, CacheEntry>,
+    total_weight: usize,
+    entry_limit: usize,
+    weight_limit: usize,
+}
+
+impl Cache {
+    pub fn new(entry_limit: usize, weight_limit: usize) -> Self {
+        Self {
+            entries: LinkedHashMap::new(),
+            total_weight: 0,
+            entry_limit,
+            weight_limit,
+        }
+    }
+
+    /// Looks up `key`, refreshing its LRU position, and either returns the
+    /// cached response or a `CompletionToken` to await in its place.
+    ///
+    /// Folding the ready-check and pending-insert into a single call (one
+    /// `entries` lookup) avoids a window, if they were two separate calls
+    /// under two separate locks, where a concurrent `complete`/`fail` could
+    /// run in between and clobber a just-resolved entry with a fresh
+    /// pending one.
+    pub fn get_or_subscribe(&mut self, key: String) -> Lookup {
+        match self.entries.get_refresh(&key) {
+            Some(CacheEntry::Ready(response)) => return Lookup::Ready(response.clone()),
+            Some(CacheEntry::Pending { followers }) => {
+                let follower = CompletionToken::new();
+                followers.push(follower.clone());
+                return Lookup::Pending(follower, false);
+            }
+            None => {}
+        }
+
+        if self.pending_count() >= self.entry_limit {
+            return Lookup::Rejected;
+        }
+
+        let leader = CompletionToken::new();
+        self.entries
+            .insert(key, CacheEntry::Pending { followers: Vec::new() });
+
+        Lookup::Pending(leader, true)
+    }
+
+    fn pending_count(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|(_, entry)| matches!(entry, CacheEntry::Pending { .. }))
+            .count()
+    }
+
+    /// Called by the leading caller once its request resolved: wakes every
+    /// follower waiting on `key` with the same response, caches it for
+    /// reuse, and evicts the least-recently-used entries if needed.
+    pub fn complete(&mut self, key: String, response: Response) {
+        if let Some(CacheEntry::Pending { followers }) = self.entries.remove(&key) {
+            for follower in followers {
+                follower.set(response.clone());
+            }
+        }
+
+        self.total_weight += response.weight();
+        self.entries.insert(key, CacheEntry::Ready(response));
+
+        self.evict();
+    }
+
+    /// Called by the leading caller if its request failed (e.g. the worker
+    /// disconnected): drops the pending entry so its followers observe a
+    /// disconnect too, rather than waiting on a response that is never
+    /// coming.
+    pub fn fail(&mut self, key: &str) {
+        self.entries.remove(key);
+    }
+
+    fn evict(&mut self) {
+        // Evict the least-recently-used *resolved* entries until both
+        // limits are satisfied. In-flight entries are skipped: they carry
+        // zero weight and evicting one would strand its followers without
+        // actually freeing anything.
+        while self.entries.len() > self.entry_limit || self.total_weight > self.weight_limit {
+            let oldest_ready = self
+                .entries
+                .iter()
+                .find(|(_, entry)| matches!(entry, CacheEntry::Ready(_)))
+                .map(|(key, _)| key.clone());
+
+            let Some(key) = oldest_ready else {
+                // Nothing resolved left to evict.
+                break;
+            };
+
+            if let Some(CacheEntry::Ready(response)) = self.entries.remove(&key) {
+                self.total_weight -= response.weight();
+            }
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use hdrhistogram::Histogram;
+use std::mem;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Two histograms of observed request latency, rotated on a fixed interval
+/// so old samples eventually age out instead of diluting recent behaviour
+/// forever.
+#[derive(Debug)]
+struct RotatingHistogram {
+    current: Histogram<u64>,
+    previous: Histogram<u64>,
+    rotated_at: Instant,
+}
+
+impl RotatingHistogram {
+    const ROTATE_INTERVAL: Duration = Duration::from_secs(10);
+
+    fn new() -> Self {
+        Self {
+            current: Histogram::new(3).expect("valid histogram"),
+            previous: Histogram::new(3).expect("valid histogram"),
+            rotated_at: Instant::now(),
+        }
+    }
+
+    fn maybe_rotate(&mut self) {
+        if self.rotated_at.elapsed() >= Self::ROTATE_INTERVAL {
+            let fresh = Histogram::new(3).expect("valid histogram");
+            self.previous = mem::replace(&mut self.current, fresh);
+            self.rotated_at = Instant::now();
+        }
+    }
+
+    fn record(&mut self, latency: Duration) {
+        self.maybe_rotate();
+        let _ = self.current.record(latency.as_micros() as u64);
+    }
+
+    /// Latency, in microseconds, at `quantile`. Falls back to the previous
+    /// window immediately after a rotation, before `current` has collected
+    /// any samples of its own.
+    fn value_at_quantile(&mut self, quantile: f64) -> u64 {
+        self.maybe_rotate();
+
+        match self.current.value_at_quantile(quantile) {
+            0 => self.previous.value_at_quantile(quantile),
+            value => value,
+        }
+    }
+}
+
+/// Latency-based hedging: if a request hasn't completed by the configured
+/// percentile of recently observed latency, a duplicate is dispatched and
+/// whichever copy resolves first wins. Hedging is capped to a fraction of
+/// in-flight requests so a slowdown storm can't double all traffic.
+#[derive(Debug)]
+pub struct Hedge {
+    histogram: Mutex<RotatingHistogram>,
+    quantile: f64,
+    max_ratio: f64,
+    in_flight: AtomicUsize,
+    hedged: AtomicUsize,
+    floor: Duration,
+}
+
+impl Hedge {
+    pub fn new(quantile: f64, max_ratio: f64) -> Self {
+        Self {
+            histogram: Mutex::new(RotatingHistogram::new()),
+            quantile,
+            max_ratio,
+            in_flight: AtomicUsize::new(0),
+            hedged: AtomicUsize::new(0),
+            // Lower bound on every hedge delay, and the delay used on its
+            // own while no latency has been recorded yet.
+            floor: Duration::from_millis(50),
+        }
+    }
+
+    pub fn record(&self, latency: Duration) {
+        self.histogram.lock().unwrap().record(latency);
+    }
+
+    pub fn delay(&self) -> Duration {
+        let micros = self.histogram.lock().unwrap().value_at_quantile(self.quantile);
+
+        if micros == 0 {
+            self.floor
+        } else {
+            Duration::from_micros(micros).max(self.floor)
+        }
+    }
+
+    pub fn enter(&self) {
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn leave(&self) {
+        self.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Reserves a hedge slot for the caller, unless doing so would push the
+    /// fraction of hedged in-flight requests past `max_ratio`. The caller
+    /// must pair a successful reservation with [`Hedge::release`].
+    pub fn try_reserve(&self) -> bool {
+        let in_flight = self.in_flight.load(Ordering::Relaxed).max(1);
+        let hedged = self.hedged.load(Ordering::Relaxed);
+
+        if (hedged as f64 + 1.0) / in_flight as f64 > self.max_ratio {
+            return false;
+        }
+
+        self.hedged.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+
+    pub fn release(&self) {
+        self.hedged.fetch_sub(1, Ordering::Relaxed);
+    }
+}
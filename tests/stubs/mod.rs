@@ -0,0 +1,10 @@
+mod cache;
+mod hedge;
+mod request;
+mod response;
+mod state;
+mod watch;
+
+pub use request::Request;
+pub use response::Response;
+pub use state::State;
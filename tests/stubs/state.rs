@@ -1,12 +1,20 @@
+use super::cache::{Cache, Lookup};
+use super::hedge::Hedge;
+use super::watch::{ResponseWatcher, Watch};
 use crate::{Request, Response};
 use async_channel::{Receiver, Sender};
-use completion_token::CompletionToken;
+use completion_token::{CompletionToken, RecvError};
+use smol_timeout::TimeoutExt;
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 use thiserror::Error;
 
 #[derive(Debug, Clone)]
 pub struct State {
     inner: Arc<Mutex<Option<Response>>>,
+    cache: Arc<Mutex<Cache>>,
+    hedge: Option<Arc<Hedge>>,
+    watch: Watch,
 
     tx: Sender<(Request, CompletionToken<Response>)>,
     rx: Receiver<(Request, CompletionToken<Response>)>,
@@ -16,6 +24,15 @@ pub struct State {
 pub enum StateError {
     #[error("can't get response")]
     GetResponseError,
+
+    #[error("worker disconnected before the request completed")]
+    Disconnected(#[from] RecvError),
+
+    #[error("worker channel closed")]
+    ChannelClosed,
+
+    #[error("too many distinct requests already in flight")]
+    CacheFull,
 }
 
 impl Default for State {
@@ -26,17 +43,63 @@ impl Default for State {
 
 impl State {
     pub fn new() -> Self {
+        Self::with_cache_limits(usize::MAX, usize::MAX)
+    }
+
+    /// Like [`State::new`], but bounds the response cache to at most
+    /// `entries` entries and `weight` total [`Weight`](super::cache::Weight).
+    pub fn with_cache_limits(entries: usize, weight: usize) -> Self {
         let (tx, rx) = async_channel::unbounded();
+        Self::with_channel(tx, rx, Cache::new(entries, weight))
+    }
+
+    /// Like [`State::new`], but the request channel only holds `capacity`
+    /// outstanding requests. Once full, `make_request` awaits until the
+    /// worker frees a slot instead of queuing without bound.
+    pub fn bounded(capacity: usize) -> Self {
+        let (tx, rx) = async_channel::bounded(capacity);
+        Self::with_channel(tx, rx, Cache::new(usize::MAX, usize::MAX))
+    }
+
+    fn with_channel(
+        tx: Sender<(Request, CompletionToken<Response>)>,
+        rx: Receiver<(Request, CompletionToken<Response>)>,
+        cache: Cache,
+    ) -> Self {
         Self {
             inner: Arc::new(Mutex::new(None)),
+            cache: Arc::new(Mutex::new(cache)),
+            hedge: None,
+            watch: Watch::new(),
             tx,
             rx,
         }
     }
 
+    /// Enables latency-based hedging: if a dispatched request hasn't
+    /// completed by the `quantile` of recently observed latency, a
+    /// duplicate is sent and whichever copy resolves first wins. At most
+    /// `max_ratio` of in-flight requests may be hedged at once.
+    pub fn with_hedge(mut self, quantile: f64, max_ratio: f64) -> Self {
+        self.hedge = Some(Arc::new(Hedge::new(quantile, max_ratio)));
+        self
+    }
+
     async fn set(&self, value: Response) {
         let mut inner = self.inner.lock().unwrap();
-        *inner = Some(value);
+        *inner = Some(value.clone());
+        drop(inner);
+
+        self.watch.set(value);
+    }
+
+    /// Returns a watcher over the latest response: unlike [`State::get_response`],
+    /// it can be read any number of times, and its `changed()` resolves
+    /// whenever a newer response has been stored since it last looked.
+    /// Only the latest response is kept, so several updates in quick
+    /// succession are observed as a single change.
+    pub fn subscribe(&self) -> ResponseWatcher {
+        self.watch.subscribe()
     }
 
     // Spawn thie worker in an executor
@@ -57,20 +120,146 @@ impl State {
         Ok(())
     }
 
-    async fn send(&self, request: Request, token: CompletionToken<Response>) {
-        // Send work to the worker
-        let _ = self.tx.send((request, token)).await;
+    // Simulates a worker that receives a request and then crashes before
+    // ever responding, dropping its `CompletionToken` clone without `set`.
+    pub async fn worker_without_responding(&self) -> Result<(), ()> {
+        let _ = self.rx.recv().await;
+
+        Ok(())
+    }
+
+    // Like `worker`, but artificially slow: useful for exercising hedging,
+    // where a duplicate request should win the race against this one.
+    pub async fn worker_delayed(&self, delay: core::time::Duration) -> Result<(), ()> {
+        if let Ok((request, token)) = self.rx.recv().await {
+            async_std::task::sleep(delay).await;
+
+            let mut response = Response::new();
+            response.value = request.value;
+
+            self.set(response.clone()).await;
+            token.set(response);
+        }
+
+        Ok(())
+    }
+
+    async fn send(
+        &self,
+        request: Request,
+        token: CompletionToken<Response>,
+    ) -> Result<(), StateError> {
+        // Send work to the worker. For a bounded channel this also applies
+        // backpressure: it awaits until the worker frees a slot rather than
+        // queuing without bound.
+        self.tx
+            .send((request, token))
+            .await
+            .map_err(|_| StateError::ChannelClosed)
     }
 
-    pub async fn make_request(&self, request: Request) -> Response {
-        let token = CompletionToken::new();
+    pub async fn make_request(&self, request: Request) -> Result<Response, StateError> {
+        let key = request.value.clone();
+
+        // A previously resolved, still-cached response can be served
+        // without ever going back to the worker. Coalesce concurrent
+        // identical requests onto a single in-flight request: the leader
+        // dispatches to the worker, everyone else just waits on a token the
+        // leader completes once the real response lands. The ready-check
+        // and pending-insert happen under a single lock acquisition so a
+        // concurrent `complete`/`fail` can't land in between.
+        let (token, is_leader) = match self.cache.lock().unwrap().get_or_subscribe(key.clone()) {
+            Lookup::Ready(response) => return Ok(response),
+            Lookup::Pending(token, is_leader) => (token, is_leader),
+            Lookup::Rejected => return Err(StateError::CacheFull),
+        };
+
+        if !is_leader {
+            // When the request has been processed, unblock the caller.
+            // This won't complete unless `worker()` is executing in a parallel task,
+            // and resolves to an error if the worker is dropped beforehand.
+            return Ok(token.await?);
+        }
 
-        // Send the request
-        self.send(request, token.clone()).await;
+        if let Err(err) = self.send(request.clone(), token.clone()).await {
+            self.cache.lock().unwrap().fail(&key);
+            return Err(err);
+        }
+
+        let start = Instant::now();
+        let result = match &self.hedge {
+            Some(hedge) => {
+                hedge.enter();
+                let result = self
+                    .make_leader_request_hedged(hedge, &request, token, start)
+                    .await;
+                hedge.leave();
+                result
+            }
+            None => token.await.map_err(StateError::from),
+        };
+
+        match result {
+            Ok(response) => {
+                self.cache.lock().unwrap().complete(key, response.clone());
+                Ok(response)
+            }
+            Err(err) => {
+                self.cache.lock().unwrap().fail(&key);
+                Err(err)
+            }
+        }
+    }
+
+    /// Awaits `token`, dispatching a duplicate `request` if it hasn't
+    /// resolved within the configured hedge delay. Records the observed
+    /// latency into `hedge`'s histogram so future delays track recent
+    /// behaviour.
+    ///
+    /// `token` is owned and consumed here rather than kept alive by the
+    /// caller, the same way the non-hedged path consumes it: otherwise it
+    /// would always be one handle too many for `Arc::strong_count` to ever
+    /// observe every dispatched worker having disconnected. The hedge-delay
+    /// race itself polls `token` through a mutable reference instead of a
+    /// clone, since a raced clone's `Drop` would look like the token's last
+    /// remaining handle going away the moment the race is lost, even though
+    /// nothing has actually disconnected and a duplicate is about to be
+    /// dispatched.
+    async fn make_leader_request_hedged(
+        &self,
+        hedge: &Arc<Hedge>,
+        request: &Request,
+        mut token: CompletionToken<Response>,
+        start: Instant,
+    ) -> Result<Response, StateError> {
+        let result = if hedge.try_reserve() {
+            let delay = hedge.delay();
+
+            let result = match (&mut token).timeout(delay).await {
+                Some(result) => result.map_err(StateError::from),
+                None => {
+                    // The original request is slow: dispatch a duplicate
+                    // and race whichever copy resolves first. Both copies
+                    // complete the same shared token, so the loser's
+                    // completion is simply left unconsumed.
+                    match self.send(request.clone(), token.clone()).await {
+                        Ok(()) => token.await.map_err(StateError::from),
+                        Err(err) => Err(err),
+                    }
+                }
+            };
+
+            hedge.release();
+            result
+        } else {
+            token.await.map_err(StateError::from)
+        };
+
+        if result.is_ok() {
+            hedge.record(start.elapsed());
+        }
 
-        // When the request has been processed, unblock the caller.
-        // This won't complete unless `worker()` is executing in a parallel task.
-        token.await
+        result
     }
 
     pub async fn get_response(&self) -> Result<Response, StateError> {
@@ -0,0 +1,114 @@
+use crate::Response;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+
+#[derive(Debug)]
+struct WatchState {
+    value: Mutex<Response>,
+    generation: AtomicU64,
+    wakers: Mutex<Vec<Waker>>,
+}
+
+/// A cell holding the latest [`Response`], broadcasting each update to any
+/// number of [`ResponseWatcher`]s without ever consuming it, unlike
+/// `State`'s destructive `get_response`.
+#[derive(Debug, Clone)]
+pub struct Watch {
+    state: Arc<WatchState>,
+}
+
+impl Watch {
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(WatchState {
+                value: Mutex::new(Response::default()),
+                generation: AtomicU64::new(0),
+                wakers: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// Stores `value` as the latest observation, advancing the generation
+    /// counter and waking every watcher parked in `changed()`.
+    pub fn set(&self, value: Response) {
+        *self.state.value.lock().unwrap() = value;
+        self.state.generation.fetch_add(1, Ordering::SeqCst);
+
+        for waker in self.state.wakers.lock().unwrap().drain(..) {
+            waker.wake();
+        }
+    }
+
+    /// Returns a new watcher that immediately sees the current value (or
+    /// the default `Response` if nothing has been set yet).
+    pub fn subscribe(&self) -> ResponseWatcher {
+        ResponseWatcher {
+            state: self.state.clone(),
+            seen: self.state.generation.load(Ordering::SeqCst),
+        }
+    }
+}
+
+impl Default for Watch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A read-only view onto a [`Watch`]'s latest value.
+#[derive(Debug)]
+pub struct ResponseWatcher {
+    state: Arc<WatchState>,
+    seen: u64,
+}
+
+impl ResponseWatcher {
+    /// Returns a read guard over the current value.
+    pub fn borrow(&self) -> MutexGuard<'_, Response> {
+        self.state.value.lock().unwrap()
+    }
+
+    /// Resolves once a value newer than the last one this watcher observed
+    /// has been stored.
+    pub fn changed(&mut self) -> Changed<'_> {
+        Changed { watcher: self }
+    }
+}
+
+pub struct Changed<'a> {
+    watcher: &'a mut ResponseWatcher,
+}
+
+impl<'a> Future for Changed<'a> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        let current = this.watcher.state.generation.load(Ordering::SeqCst);
+        if current > this.watcher.seen {
+            this.watcher.seen = current;
+            return Poll::Ready(());
+        }
+
+        this.watcher
+            .state
+            .wakers
+            .lock()
+            .unwrap()
+            .push(cx.waker().clone());
+
+        // Re-check after registering, in case `Watch::set` ran between the
+        // first load above and the waker being registered.
+        let current = this.watcher.state.generation.load(Ordering::SeqCst);
+        if current > this.watcher.seen {
+            this.watcher.seen = current;
+            return Poll::Ready(());
+        }
+
+        Poll::Pending
+    }
+}
@@ -4,8 +4,9 @@ extern crate rstest;
 mod stubs;
 use stubs::{Request, Response, State};
 
-use completion_token::CompletionToken;
+use completion_token::{CompletionToken, RecvError};
 use core::time::Duration;
+use futures_util::StreamExt;
 use smol_timeout::TimeoutExt;
 
 use std::error::Error;
@@ -33,7 +34,7 @@ async fn test_state() -> Result<(), Box<dyn Error>> {
     let response = response
         .timeout(Duration::from_secs(1))
         .await
-        .expect("timeout exceeded");
+        .expect("timeout exceeded")?;
 
     // Check for consistency
     assert_eq!(state.get_response().await?, response);
@@ -45,6 +46,56 @@ async fn test_state() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+#[rstest]
+async fn test_state_worker_disconnected() -> Result<(), Box<dyn Error>> {
+    let state = State::new();
+
+    // Fire off the request before any worker exists to service it.
+    let request_state = state.clone();
+    let response = async_std::task::spawn(async move {
+        let request = Request::new("anyone there?");
+        request_state.make_request(request).await
+    });
+
+    // A worker that receives the request, then disappears without ever
+    // calling `token.set(..)` on it (e.g. it crashed mid-request).
+    let dead_worker_state = state.clone();
+    async_std::task::spawn(async move {
+        let _ = dead_worker_state.worker_without_responding().await;
+    })
+    .timeout(Duration::from_secs(1))
+    .await
+    .expect("timeout exceeded");
+
+    let result = response
+        .timeout(Duration::from_secs(1))
+        .await
+        .expect("timeout exceeded");
+
+    assert!(result.is_err());
+    Ok(())
+}
+
+#[rstest]
+async fn test_completion_token_disconnected() -> Result<(), Box<dyn Error>> {
+    let token = CompletionToken::<&str>::new();
+
+    let setter = token.clone();
+    async_std::task::spawn(async move {
+        // Drop the only other handle without ever calling `set`.
+        drop(setter);
+    })
+    .await;
+
+    let result = token
+        .timeout(Duration::from_secs(1))
+        .await
+        .expect("timeout exceeded");
+
+    assert_eq!(result, Err(RecvError::Disconnected));
+    Ok(())
+}
+
 #[rstest]
 async fn test_completion_token() -> Result<(), Box<dyn Error>> {
     let token = CompletionToken::<&str>::new();
@@ -62,7 +113,7 @@ async fn test_completion_token() -> Result<(), Box<dyn Error>> {
     // ... and let the token can now complete
     let result = token.timeout(Duration::from_secs(1)).await;
 
-    assert_eq!(result, Some("Hello World!"));
+    assert_eq!(result, Some(Ok("Hello World!")));
     Ok(())
 }
 
@@ -79,7 +130,7 @@ async fn test_cloned_completion_token() -> Result<(), Box<dyn Error>> {
     // ... and the original token can now complete
     let result = token.timeout(Duration::from_secs(1)).await;
 
-    assert_eq!(result, Some("Hello World!"));
+    assert_eq!(result, Some(Ok("Hello World!")));
     Ok(())
 }
 
@@ -92,7 +143,7 @@ async fn test_threaded_token() -> Result<(), Box<dyn Error>> {
 
     let result = token.timeout(Duration::from_secs(1)).await;
 
-    assert_eq!(result, Some("Hello World!"));
+    assert_eq!(result, Some(Ok("Hello World!")));
     Ok(())
 }
 
@@ -106,7 +157,8 @@ async fn test_asyncstd_token() -> Result<(), Box<dyn Error>> {
     let result = token
         .timeout(Duration::from_secs(1))
         .await
-        .expect("timeout exceeded");
+        .expect("timeout exceeded")
+        .expect("token disconnected");
 
     assert_eq!(result, "Hello World!");
     Ok(())
@@ -122,7 +174,8 @@ async fn test_tokio_token() -> Result<(), Box<dyn Error>> {
     let result = token
         .timeout(Duration::from_secs(1))
         .await
-        .expect("timeout exceeded");
+        .expect("timeout exceeded")
+        .expect("token disconnected");
 
     assert_eq!(result, "Hello World!");
     Ok(())
@@ -142,6 +195,7 @@ async fn test_take_twice() -> Result<(), Box<dyn Error>> {
         t1.timeout(Duration::from_secs(1))
             .await
             .expect("timeout 1 exceeded")
+            .expect("token 1 disconnected")
     );
 
     token.set("World");
@@ -150,11 +204,343 @@ async fn test_take_twice() -> Result<(), Box<dyn Error>> {
         t2.timeout(Duration::from_secs(1))
             .await
             .expect("timeout 2 exceeded")
+            .expect("token 2 disconnected")
     );
 
     Ok(())
 }
 
+#[rstest]
+async fn test_concurrent_waiters_all_wake() -> Result<(), Box<dyn Error>> {
+    const WAITERS: usize = 3;
+
+    let token = CompletionToken::<&str>::new();
+    let (done_tx, done_rx) = async_channel::unbounded();
+
+    // Spawn several tasks that all poll the same token before it completes,
+    // each signalling back once it actually receives a value.
+    let waiters: Vec<_> = (0..WAITERS)
+        .map(|_| {
+            let token = token.clone();
+            let done_tx = done_tx.clone();
+            async_std::task::spawn(async move {
+                let value = token
+                    .timeout(Duration::from_secs(1))
+                    .await
+                    .expect("timeout exceeded")
+                    .expect("token disconnected");
+                let _ = done_tx.send(()).await;
+                value
+            })
+        })
+        .collect();
+    drop(done_tx);
+
+    // Give every waiter a chance to register its waker before completing.
+    async_std::task::sleep(Duration::from_millis(50)).await;
+
+    // Only the single-consumer `take` semantics let one `set` unblock one
+    // waiter at a time, but every waiter must be woken (and re-register)
+    // rather than leaving some of them parked forever. Wait for each
+    // hand-off to be observed before the next `set`: otherwise back-to-back
+    // `set` calls can collapse onto the same waiter before the executor
+    // reschedules it, leaving the rest parked.
+    for _ in 0..WAITERS {
+        token.set("Hello World!");
+        done_rx.recv().await.expect("waiter did not observe value");
+    }
+
+    for waiter in waiters {
+        assert_eq!(waiter.await, "Hello World!");
+    }
+
+    Ok(())
+}
+
+#[rstest]
+async fn test_request_coalescing() -> Result<(), Box<dyn Error>> {
+    let state = State::new();
+
+    let worker_state = state.clone();
+    let worker = async_std::task::spawn(async move { worker_state.worker().await });
+
+    let request_value = "coalesce me";
+    let responses: Vec<_> = (0..3)
+        .map(|_| {
+            let state = state.clone();
+            async_std::task::spawn(
+                async move { state.make_request(Request::new(request_value)).await },
+            )
+        })
+        .collect();
+
+    let _ = worker
+        .timeout(Duration::from_secs(1))
+        .await
+        .expect("timeout exceeded");
+
+    for response in responses {
+        let response = response
+            .timeout(Duration::from_secs(1))
+            .await
+            .expect("timeout exceeded")?;
+        assert_eq!(response.value, request_value);
+    }
+
+    // A second worker should see no further requests queued: the three
+    // identical requests above coalesced onto the single trip already
+    // serviced.
+    let second_worker_state = state.clone();
+    let result = second_worker_state
+        .worker()
+        .timeout(Duration::from_millis(50))
+        .await;
+    assert!(result.is_none());
+
+    Ok(())
+}
+
+#[rstest]
+async fn test_cache_eviction() -> Result<(), Box<dyn Error>> {
+    let state = State::with_cache_limits(2, usize::MAX);
+
+    for value in ["a", "b", "c"] {
+        let worker_state = state.clone();
+        let worker = async_std::task::spawn(async move { worker_state.worker().await });
+
+        let response = state
+            .make_request(Request::new(value))
+            .timeout(Duration::from_secs(1))
+            .await
+            .expect("timeout exceeded")?;
+        assert_eq!(response.value, value);
+
+        let _ = worker
+            .timeout(Duration::from_secs(1))
+            .await
+            .expect("timeout exceeded");
+    }
+
+    // "a" was the least-recently-used entry once the cache filled past its
+    // 2-entry limit, so it should have been evicted and require a fresh
+    // trip to the worker.
+    let worker_state = state.clone();
+    let worker = async_std::task::spawn(async move { worker_state.worker().await });
+
+    let response = state
+        .make_request(Request::new("a"))
+        .timeout(Duration::from_secs(1))
+        .await
+        .expect("timeout exceeded")?;
+    assert_eq!(response.value, "a");
+
+    let _ = worker
+        .timeout(Duration::from_secs(1))
+        .await
+        .expect("timeout exceeded");
+
+    Ok(())
+}
+
+#[rstest]
+async fn test_cache_bounds_pending_entries() -> Result<(), Box<dyn Error>> {
+    // In-flight entries carry zero weight and are never evicted, so without
+    // a separate cap a burst of distinct, never-resolving requests could
+    // grow the cache unbounded even though `entry_limit` is 2.
+    let state = State::with_cache_limits(2, usize::MAX);
+
+    // No worker ever drains these, so both stay pending forever.
+    let _first = async_std::task::spawn({
+        let state = state.clone();
+        async move { state.make_request(Request::new("a")).await }
+    });
+    let _second = async_std::task::spawn({
+        let state = state.clone();
+        async move { state.make_request(Request::new("b")).await }
+    });
+
+    // Give the two leaders a chance to register their pending entries.
+    async_std::task::sleep(Duration::from_millis(50)).await;
+
+    let result = state.make_request(Request::new("c")).await;
+    assert!(result.is_err());
+
+    Ok(())
+}
+
+#[rstest]
+async fn test_bounded_channel_applies_backpressure() -> Result<(), Box<dyn Error>> {
+    let state = State::bounded(1);
+
+    // Fill the single slot in the channel.
+    let first_state = state.clone();
+    let first = async_std::task::spawn(async move {
+        first_state.make_request(Request::new("first")).await
+    });
+
+    // The channel is now full, so a second distinct request should block
+    // on `send` rather than queuing without bound.
+    let second_state = state.clone();
+    let second = async_std::task::spawn(async move {
+        second_state.make_request(Request::new("second")).await
+    });
+
+    let blocked = second
+        .timeout(Duration::from_millis(100))
+        .await;
+    assert!(
+        blocked.is_none(),
+        "second request should still be blocked on the full channel"
+    );
+
+    // Draining one slot via the worker lets both requests eventually
+    // complete.
+    let worker_state = state.clone();
+    let _ = async_std::task::spawn(async move { worker_state.worker().await }).await;
+    let worker_state = state.clone();
+    let _ = async_std::task::spawn(async move { worker_state.worker().await }).await;
+
+    let first = first
+        .timeout(Duration::from_secs(1))
+        .await
+        .expect("timeout exceeded")?;
+    assert_eq!(first.value, "first");
+
+    Ok(())
+}
+
+#[rstest]
+async fn test_request_hedging() -> Result<(), Box<dyn Error>> {
+    // `max_ratio` of 1.0 lets a single in-flight request always reserve a
+    // hedge slot.
+    let state = State::new().with_hedge(0.9, 1.0);
+
+    // The leader's dispatch lands on a deliberately slow worker.
+    let slow_worker_state = state.clone();
+    async_std::task::spawn(async move {
+        let _ = slow_worker_state
+            .worker_delayed(Duration::from_millis(300))
+            .await;
+    });
+
+    let started = std::time::Instant::now();
+    let request_state = state.clone();
+    let response = async_std::task::spawn(async move {
+        request_state
+            .make_request(Request::new("slow then fast"))
+            .await
+    });
+
+    // Give the hedge's floor delay time to fire and dispatch a duplicate,
+    // then let a fast worker service it.
+    async_std::task::sleep(Duration::from_millis(100)).await;
+    let fast_worker_state = state.clone();
+    async_std::task::spawn(async move {
+        let _ = fast_worker_state.worker().await;
+    });
+
+    let response = response
+        .timeout(Duration::from_secs(1))
+        .await
+        .expect("timeout exceeded")?;
+
+    assert_eq!(response.value, "slow then fast");
+    assert!(
+        started.elapsed() < Duration::from_millis(300),
+        "the hedged duplicate should have won the race against the slow worker"
+    );
+
+    Ok(())
+}
+
+#[rstest]
+async fn test_request_hedging_disconnected() -> Result<(), Box<dyn Error>> {
+    // `max_ratio` of 1.0 lets a single in-flight request always reserve a
+    // hedge slot.
+    let state = State::new().with_hedge(0.9, 1.0);
+
+    // No worker ever responds. This one drops its handle as soon as it
+    // receives the request, which already disconnects the token, normally
+    // before the hedge delay elapses.
+    let dead_worker_state = state.clone();
+    async_std::task::spawn(async move {
+        let _ = dead_worker_state.worker_without_responding().await;
+    });
+
+    let request_state = state.clone();
+    let response = async_std::task::spawn(async move {
+        request_state
+            .make_request(Request::new("nobody home"))
+            .await
+    });
+
+    // Should a hedged duplicate be dispatched anyway, a second worker that
+    // also disconnects without responding is there to receive it.
+    async_std::task::sleep(Duration::from_millis(100)).await;
+    let other_dead_worker_state = state.clone();
+    async_std::task::spawn(async move {
+        let _ = other_dead_worker_state.worker_without_responding().await;
+    });
+
+    let result = response
+        .timeout(Duration::from_secs(1))
+        .await
+        .expect("timeout exceeded");
+
+    assert!(result.is_err());
+    Ok(())
+}
+
+#[rstest]
+async fn test_stream_collects_repeated_sets() -> Result<(), Box<dyn Error>> {
+    let mut token = CompletionToken::<&str>::new();
+
+    let setter = token.clone();
+    async_std::task::spawn(async move {
+        for value in ["one", "two", "three"] {
+            setter.set(value);
+            // Give the stream a chance to drain each value before the next
+            // `set` overwrites it.
+            async_std::task::sleep(Duration::from_millis(10)).await;
+        }
+    });
+
+    let mut values = Vec::new();
+    while values.len() < 3 {
+        if let Some(value) = token
+            .next()
+            .timeout(Duration::from_secs(1))
+            .await
+            .expect("timeout exceeded")
+        {
+            values.push(value);
+        }
+    }
+
+    assert_eq!(values, vec!["one", "two", "three"]);
+    Ok(())
+}
+
+#[rstest]
+async fn test_stream_ends_on_disconnect() -> Result<(), Box<dyn Error>> {
+    let mut token = CompletionToken::<&str>::new();
+
+    let setter = token.clone();
+    async_std::task::spawn(async move {
+        drop(setter);
+    })
+    .await;
+
+    let result = token
+        .next()
+        .timeout(Duration::from_secs(1))
+        .await
+        .expect("timeout exceeded");
+
+    assert_eq!(result, None);
+    Ok(())
+}
+
 #[rstest]
 async fn test_set_twice() -> Result<(), Box<dyn Error>> {
     let token = CompletionToken::<&str>::new();
@@ -168,7 +554,110 @@ async fn test_set_twice() -> Result<(), Box<dyn Error>> {
             .timeout(Duration::from_secs(1))
             .await
             .expect("timeout exceeded")
+            .expect("token disconnected")
     );
 
     Ok(())
 }
+
+#[rstest]
+async fn test_watcher_sees_current_value_immediately() -> Result<(), Box<dyn Error>> {
+    let state = State::new();
+
+    // No response has ever been stored, so a brand new watcher sees the
+    // default value without needing to wait for `changed()`.
+    let watcher = state.subscribe();
+    assert_eq!(watcher.borrow().value, "");
+
+    Ok(())
+}
+
+#[rstest]
+async fn test_multiple_watchers_observe_successive_responses() -> Result<(), Box<dyn Error>> {
+    let state = State::new();
+
+    let mut first_watcher = state.subscribe();
+    let mut second_watcher = state.subscribe();
+
+    let worker_state = state.clone();
+    async_std::task::spawn(async move {
+        let _ = worker_state.worker().await;
+        let _ = worker_state.worker().await;
+    });
+
+    // Barrier: the producer only dispatches "second" once every watcher has
+    // actually observed "first". Without this, the producer can race ahead
+    // and the generation counter jumps straight from 0 to 2, so a watcher
+    // would never see the intermediate value.
+    let (seen_first_tx, seen_first_rx) = async_channel::bounded(2);
+
+    let first_seen_tx = seen_first_tx.clone();
+    let first = async_std::task::spawn(async move {
+        first_watcher
+            .changed()
+            .timeout(Duration::from_secs(1))
+            .await
+            .expect("timeout exceeded");
+        assert_eq!(first_watcher.borrow().value, "first");
+        let _ = first_seen_tx.send(()).await;
+        first_watcher
+    });
+
+    let second_seen_tx = seen_first_tx.clone();
+    let second = async_std::task::spawn(async move {
+        second_watcher
+            .changed()
+            .timeout(Duration::from_secs(1))
+            .await
+            .expect("timeout exceeded");
+        assert_eq!(second_watcher.borrow().value, "first");
+        let _ = second_seen_tx.send(()).await;
+        second_watcher
+    });
+    drop(seen_first_tx);
+
+    let request_state = state.clone();
+    async_std::task::spawn(async move {
+        let _ = request_state
+            .make_request(Request::new("first"))
+            .await;
+
+        seen_first_rx
+            .recv()
+            .await
+            .expect("watcher did not observe value");
+        seen_first_rx
+            .recv()
+            .await
+            .expect("watcher did not observe value");
+
+        let _ = request_state
+            .make_request(Request::new("second"))
+            .await;
+    });
+
+    let mut first_watcher = first
+        .timeout(Duration::from_secs(1))
+        .await
+        .expect("timeout exceeded");
+    let mut second_watcher = second
+        .timeout(Duration::from_secs(1))
+        .await
+        .expect("timeout exceeded");
+
+    first_watcher
+        .changed()
+        .timeout(Duration::from_secs(1))
+        .await
+        .expect("timeout exceeded");
+    assert_eq!(first_watcher.borrow().value, "second");
+
+    second_watcher
+        .changed()
+        .timeout(Duration::from_secs(1))
+        .await
+        .expect("timeout exceeded");
+    assert_eq!(second_watcher.borrow().value, "second");
+
+    Ok(())
+}